@@ -0,0 +1,226 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Key/value pairs accumulated from every parsed layer. Keys are stored as
+/// `section.key`; later layers override earlier ones and
+/// `%unset` removes a key an earlier layer set, mirroring Mercurial's
+/// layered config model.
+#[derive(Default, Debug)]
+struct RawConfig {
+    entries: HashMap<String, Vec<String>>,
+}
+
+impl RawConfig {
+    fn set(&mut self, key: String, values: Vec<String>) {
+        self.entries.insert(key, values);
+    }
+
+    fn unset(&mut self, key: &str) {
+        self.entries.remove(key);
+    }
+}
+
+/// Parses `path`, splicing in `%include <other>` files (resolved relative to
+/// `path`'s directory) depth-first so an outer file's later assignments
+/// still win over an included one, and applying `%unset <key>` as it's
+/// encountered.
+fn parse_file(path: &Path, out: &mut RawConfig) -> Result<()> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("read config {}", path.display()))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut section = String::new();
+
+    for (lineno, raw_line) in text.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("%include") {
+            let target = dir.join(rest.trim());
+            parse_file(&target, out).with_context(|| {
+                format!("{}:{}: %include {}", path.display(), lineno + 1, rest.trim())
+            })?;
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("%unset") {
+            let key = rest.trim();
+            let qualified = qualify(&section, key);
+            out.unset(&qualified);
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].trim().to_string();
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            bail!(
+                "{}:{}: expected 'key = value', got {line:?}",
+                path.display(),
+                lineno + 1
+            );
+        };
+        let key = qualify(&section, key.trim());
+        let values = value
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
+        out.set(key, values);
+    }
+
+    Ok(())
+}
+
+fn qualify(section: &str, key: &str) -> String {
+    if key.contains('.') {
+        key.to_string()
+    } else {
+        format!("{section}.{key}")
+    }
+}
+
+/// User-facing exclusion policy: ignored directory names, glob patterns, and
+/// a size window, before they've been compiled into matchers.
+#[derive(Debug, Clone, Default)]
+pub struct ExclusionConfig {
+    pub ignore_dirs: Vec<String>,
+    pub globs: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+/// Parses `paths` in order (earlier = lower priority) into one layered
+/// `ExclusionConfig`.
+pub fn load(paths: &[PathBuf]) -> Result<ExclusionConfig> {
+    let mut raw = RawConfig::default();
+    for path in paths {
+        parse_file(path, &mut raw)?;
+    }
+
+    let mut config = ExclusionConfig::default();
+    if let Some(dirs) = raw.entries.get("ignore.dirs") {
+        config.ignore_dirs = dirs.clone();
+    }
+    if let Some(globs) = raw.entries.get("exclude.glob") {
+        config.globs = globs.clone();
+    }
+    if let Some(min) = raw.entries.get("size.min").and_then(|v| v.first()) {
+        config.min_size = Some(min.parse().with_context(|| "parse size.min")?);
+    }
+    if let Some(max) = raw.entries.get("size.max").and_then(|v| v.first()) {
+        config.max_size = Some(max.parse().with_context(|| "parse size.max")?);
+    }
+    Ok(config)
+}
+
+impl ExclusionConfig {
+    /// CLI flags always win: they're appended to the config's lists and
+    /// override its size bounds outright.
+    pub fn merge_cli(
+        &mut self,
+        ignore_dirs: &[String],
+        globs: &[String],
+        min_size: Option<u64>,
+        max_size: Option<u64>,
+    ) {
+        self.ignore_dirs.extend(ignore_dirs.iter().cloned());
+        self.globs.extend(globs.iter().cloned());
+        if min_size.is_some() {
+            self.min_size = min_size;
+        }
+        if max_size.is_some() {
+            self.max_size = max_size;
+        }
+    }
+
+    pub fn compile(&self) -> Result<CompiledExclusions> {
+        let mut builder = globset::GlobSetBuilder::new();
+        for pattern in &self.globs {
+            builder.add(
+                globset::Glob::new(pattern).with_context(|| format!("invalid glob {pattern:?}"))?,
+            );
+        }
+        Ok(CompiledExclusions {
+            ignore_dirs: self.ignore_dirs.iter().cloned().collect(),
+            globs: builder.build().context("compile glob set")?,
+            min_size: self.min_size,
+            max_size: self.max_size,
+        })
+    }
+}
+
+/// Compiled, ready-to-match form of `ExclusionConfig`, built once per run.
+pub struct CompiledExclusions {
+    ignore_dirs: std::collections::HashSet<String>,
+    globs: globset::GlobSet,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+}
+
+impl CompiledExclusions {
+    pub fn empty() -> Self {
+        ExclusionConfig::default().compile().expect("empty config always compiles")
+    }
+
+    pub fn is_dir_ignored(&self, name: &str) -> bool {
+        self.ignore_dirs.contains(name)
+    }
+
+    pub fn is_path_excluded(&self, path: &Path) -> bool {
+        self.globs.is_match(path)
+    }
+
+    pub fn is_size_excluded(&self, size: u64) -> bool {
+        self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outer_assignment_overrides_included_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("base.ini"), "[ignore]\ndirs = .git\n").unwrap();
+        std::fs::write(
+            dir.path().join("outer.ini"),
+            "%include base.ini\n[ignore]\ndirs = .git,node_modules\n",
+        )
+        .unwrap();
+
+        let config = load(&[dir.path().join("outer.ini")]).unwrap();
+        assert_eq!(config.ignore_dirs, vec![".git".to_string(), "node_modules".to_string()]);
+    }
+
+    #[test]
+    fn unset_removes_a_key_set_by_an_earlier_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("base.ini"), "[size]\nmin = 10\n").unwrap();
+        std::fs::write(
+            dir.path().join("outer.ini"),
+            "%include base.ini\n%unset size.min\n",
+        )
+        .unwrap();
+
+        let config = load(&[dir.path().join("outer.ini")]).unwrap();
+        assert_eq!(config.min_size, None);
+    }
+
+    #[test]
+    fn later_config_path_overrides_an_earlier_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.ini");
+        let second = dir.path().join("second.ini");
+        std::fs::write(&first, "[size]\nmin = 10\n").unwrap();
+        std::fs::write(&second, "[size]\nmin = 20\n").unwrap();
+
+        let config = load(&[first, second]).unwrap();
+        assert_eq!(config.min_size, Some(20));
+    }
+}
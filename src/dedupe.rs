@@ -1,4 +1,7 @@
 use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -7,6 +10,32 @@ pub enum LinkType {
     HardLink,
 }
 
+const COMPARE_BUF_SIZE: usize = 64 * 1024;
+
+/// Byte-for-byte comparison used by `--paranoid` right before a link would
+/// replace a file, to catch hash collisions or bit rot the sparse/full hash
+/// pipeline can't see.
+pub fn compare_files(a: &Path, b: &Path) -> Result<bool> {
+    let mut a = BufReader::new(File::open(a).with_context(|| "open for comparison")?);
+    let mut b = BufReader::new(File::open(b).with_context(|| "open for comparison")?);
+
+    let mut buf_a = [0u8; COMPARE_BUF_SIZE];
+    let mut buf_b = [0u8; COMPARE_BUF_SIZE];
+    loop {
+        let read_a = a.read(&mut buf_a).with_context(|| "read for comparison")?;
+        let read_b = b.read(&mut buf_b).with_context(|| "read for comparison")?;
+        if read_a != read_b {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+        if buf_a[..read_a] != buf_b[..read_b] {
+            return Ok(false);
+        }
+    }
+}
+
 struct TempCleanup {
     path: PathBuf,
     armed: bool,
@@ -46,20 +75,64 @@ pub fn replace_with_link(master: &Path, target: &Path) -> Result<Option<LinkType
 
     let mut cleanup = TempCleanup::new(temp.clone());
 
-    match reflink::reflink(master, &temp) {
-        Ok(_) => {
-            std::fs::rename(&temp, target).with_context(|| "replace target with reflink")?;
-            cleanup.disarm();
-            Ok(Some(LinkType::Reflink))
-        }
+    let link_type = match reflink::reflink(master, &temp) {
+        Ok(_) => LinkType::Reflink,
         Err(_) => {
             if temp.exists() {
                 let _ = std::fs::remove_file(&temp);
             }
             std::fs::hard_link(master, &temp).with_context(|| "create hard link")?;
-            std::fs::rename(&temp, target).with_context(|| "replace target with hard link")?;
-            cleanup.disarm();
-            Ok(Some(LinkType::HardLink))
+            LinkType::HardLink
+        }
+    };
+
+    // A reflink is an independent copy that inherited the vault master's
+    // attributes, not the original target's, so it needs the target's own
+    // mode/owner/timestamps/xattrs restored before it takes the target's
+    // place. A hardlink shares the vault master's inode outright: per-file
+    // metadata is impossible over a shared inode, and "restoring" it here
+    // would instead clobber the master (and every other duplicate already
+    // linked to it) with this one target's attributes. If the target no
+    // longer exists, it was the vault master and `ensure_in_vault` already
+    // moved it in by rename; the vault copy already carries its attributes
+    // unchanged from that move, so there's nothing to restore.
+    if link_type == LinkType::Reflink && target.exists() {
+        preserve_metadata(target, &temp)
+            .with_context(|| format!("preserve metadata for {}", target.display()))?;
+    }
+
+    std::fs::rename(&temp, target).with_context(|| "replace target with link")?;
+    cleanup.disarm();
+    Ok(Some(link_type))
+}
+
+fn preserve_metadata(original: &Path, replacement: &Path) -> Result<()> {
+    let meta = std::fs::metadata(original).with_context(|| "stat original file")?;
+
+    std::fs::set_permissions(replacement, meta.permissions()).with_context(|| "chmod replacement")?;
+
+    if let Err(err) = nix::unistd::chown(
+        replacement,
+        Some(nix::unistd::Uid::from_raw(meta.uid())),
+        Some(nix::unistd::Gid::from_raw(meta.gid())),
+    ) {
+        // Only root or the owning user can change ownership; running
+        // unprivileged against someone else's files is a common,
+        // non-fatal case.
+        if err != nix::errno::Errno::EPERM {
+            return Err(err).with_context(|| "chown replacement");
         }
     }
+
+    let atime = filetime::FileTime::from_last_access_time(&meta);
+    let mtime = filetime::FileTime::from_last_modification_time(&meta);
+    filetime::set_file_times(replacement, atime, mtime).with_context(|| "set replacement timestamps")?;
+
+    for name in xattr::list(original).with_context(|| "list xattrs")? {
+        if let Some(value) = xattr::get(original, &name).with_context(|| "read xattr")? {
+            xattr::set(replacement, &name, &value).with_context(|| "copy xattr")?;
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,100 @@
+use crate::types::{Hash, HashType};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// A boxed digest algorithm. `sparse_hash`/`full_hash` dispatch through this
+/// instead of hard-coding a single digest so callers pick speed vs.
+/// collision-resistance per run.
+pub trait MyHasher: Send + Sync {
+    fn algo(&self) -> HashType;
+    fn hash_bytes(&self, data: &[u8]) -> Hash;
+}
+
+struct Blake3Hasher;
+
+impl MyHasher for Blake3Hasher {
+    fn algo(&self) -> HashType {
+        HashType::Blake3
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> Hash {
+        Hash::new(HashType::Blake3, blake3::hash(data).as_bytes())
+    }
+}
+
+struct Xxh3Hasher;
+
+impl MyHasher for Xxh3Hasher {
+    fn algo(&self) -> HashType {
+        HashType::Xxh3
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> Hash {
+        let digest = xxhash_rust::xxh3::xxh3_64(data).to_be_bytes();
+        Hash::new(HashType::Xxh3, &digest)
+    }
+}
+
+struct Crc32Hasher;
+
+impl MyHasher for Crc32Hasher {
+    fn algo(&self) -> HashType {
+        HashType::Crc32
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> Hash {
+        let digest = crc32fast::hash(data).to_be_bytes();
+        Hash::new(HashType::Crc32, &digest)
+    }
+}
+
+pub fn make_hasher(algo: HashType) -> Box<dyn MyHasher> {
+    match algo {
+        HashType::Blake3 => Box::new(Blake3Hasher),
+        HashType::Xxh3 => Box::new(Xxh3Hasher),
+        HashType::Crc32 => Box::new(Crc32Hasher),
+    }
+}
+
+/// Sampled head/middle/tail hash, cheap enough to run on every candidate in
+/// a size bucket before committing to a full read.
+const SPARSE_SAMPLE_BYTES: u64 = 64 * 1024;
+
+pub fn sparse_hash(path: &Path, size: u64, algo: HashType) -> Result<Hash> {
+    let hasher = make_hasher(algo);
+    let mut file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut sample = Vec::new();
+
+    let head_len = SPARSE_SAMPLE_BYTES.min(size) as usize;
+    let mut head = vec![0u8; head_len];
+    file.read_exact(&mut head)?;
+    sample.extend_from_slice(&head);
+
+    if size > SPARSE_SAMPLE_BYTES * 2 {
+        file.seek(SeekFrom::Start(size / 2))?;
+        let mut mid = vec![0u8; SPARSE_SAMPLE_BYTES as usize];
+        file.read_exact(&mut mid)?;
+        sample.extend_from_slice(&mid);
+    }
+
+    if size > SPARSE_SAMPLE_BYTES {
+        let tail_start = size - SPARSE_SAMPLE_BYTES;
+        file.seek(SeekFrom::Start(tail_start))?;
+        let mut tail = vec![0u8; SPARSE_SAMPLE_BYTES as usize];
+        file.read_exact(&mut tail)?;
+        sample.extend_from_slice(&tail);
+    }
+
+    sample.extend_from_slice(&size.to_le_bytes());
+    Ok(hasher.hash_bytes(&sample))
+}
+
+pub fn full_hash(path: &Path, algo: HashType) -> Result<Hash> {
+    let hasher = make_hasher(algo);
+    let mut file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(hasher.hash_bytes(&data))
+}
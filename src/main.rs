@@ -1,5 +1,8 @@
+mod config;
 mod dedupe;
 mod hasher;
+mod mime;
+mod reclaim;
 mod scanner;
 mod state;
 mod types;
@@ -14,13 +17,64 @@ use std::collections::HashMap;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 
-use crate::types::{FileMetadata, Hash};
+use crate::types::{FileMetadata, Hash, HashType};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Imprint - speed-first deduplication engine")]
 struct Args {
     #[command(subcommand)]
     command: Commands,
+
+    #[arg(
+        long,
+        global = true,
+        default_value = "blake3",
+        help = "Digest algorithm: blake3 (default, collision-resistant), xxh3 (fast, non-cryptographic), or crc32."
+    )]
+    hash: HashType,
+
+    #[arg(
+        long = "config",
+        global = true,
+        help = "Layered INI-style exclusion config file(s), applied in order given. Supports %include and %unset."
+    )]
+    config_paths: Vec<PathBuf>,
+
+    #[arg(
+        long = "ignore-dir",
+        global = true,
+        help = "Directory name to prune from the walk entirely (e.g. .git). Repeatable."
+    )]
+    ignore_dirs: Vec<String>,
+
+    #[arg(
+        long = "exclude",
+        global = true,
+        help = "Glob pattern for paths to skip (e.g. '*.tmp'). Repeatable."
+    )]
+    exclude_globs: Vec<String>,
+
+    #[arg(long, global = true, help = "Skip files smaller than this many bytes.")]
+    min_size: Option<u64>,
+
+    #[arg(long, global = true, help = "Skip files larger than this many bytes.")]
+    max_size: Option<u64>,
+
+    #[arg(
+        long,
+        global = true,
+        default_value = "",
+        help = "Comma-separated MIME type allowlist, detected by magic bytes (e.g. 'image/*,video/mp4')."
+    )]
+    only: String,
+
+    #[arg(
+        long,
+        global = true,
+        default_value = "",
+        help = "Comma-separated MIME type denylist, detected by magic bytes."
+    )]
+    skip: String,
 }
 
 #[derive(Subcommand, Debug)]
@@ -40,6 +94,41 @@ enum Commands {
         )]
         dry_run: bool,
     },
+    /// Delete or trash duplicates instead of linking them. For filesystems
+    /// without reflink support, or when you just want the space back.
+    Reclaim {
+        path: PathBuf,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "delete",
+            help = "How to remove a duplicate that isn't kept: delete (hard delete) or trash (XDG trash / --trash-dir)."
+        )]
+        delete_method: reclaim::DeleteMethod,
+        #[arg(
+            long,
+            help = "Directory to move trashed duplicates into, instead of the XDG trash."
+        )]
+        trash_dir: Option<PathBuf>,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "first",
+            help = "Which copy in a duplicate group survives: first, newest (by mtime), or shortest-path."
+        )]
+        keep: reclaim::KeepPolicy,
+        #[arg(
+            long,
+            help = "Perform byte-for-byte verification before removing a duplicate."
+        )]
+        paranoid: bool,
+        #[arg(
+            long,
+            short = 'n',
+            help = "List the duplicates that would be removed without deleting or trashing any of them."
+        )]
+        dry_run: bool,
+    },
 }
 
 fn main() {
@@ -52,24 +141,48 @@ fn main() {
 fn run() -> Result<()> {
     let args = Args::parse();
     let state = state::State::open_default()?;
+    state.check_or_set_hash_algo(args.hash)?;
+
+    let mut exclusion_config = config::load(&args.config_paths)?;
+    exclusion_config.merge_cli(&args.ignore_dirs, &args.exclude_globs, args.min_size, args.max_size);
+    let exclusions = exclusion_config.compile()?;
+    let mime_filter = mime::MimeFilter::parse(&args.only, &args.skip)?;
 
     match args.command {
         Commands::Scan { path } => {
-            let groups = scan_pipeline(&path, &state)?;
+            let groups = scan_pipeline(&path, &state, args.hash, &exclusions, &mime_filter)?;
             print_summary("scan", &groups);
         }
         Commands::Dedupe { path, paranoid, dry_run } => {
-            let groups = scan_pipeline(&path, &state)?;
+            let groups = scan_pipeline(&path, &state, args.hash, &exclusions, &mime_filter)?;
             dedupe_groups(&groups, &state, paranoid, dry_run)?;
             print_summary("dedupe", &groups);
         }
+        Commands::Reclaim {
+            path,
+            delete_method,
+            trash_dir,
+            keep,
+            paranoid,
+            dry_run,
+        } => {
+            let groups = scan_pipeline(&path, &state, args.hash, &exclusions, &mime_filter)?;
+            reclaim_groups(&groups, delete_method, trash_dir.as_deref(), keep, paranoid, dry_run)?;
+            print_summary("reclaim", &groups);
+        }
     }
 
     Ok(())
 }
 
-fn scan_pipeline(path: &Path, state: &state::State) -> Result<HashMap<Hash, Vec<PathBuf>>> {
-    let size_groups = scanner::group_by_size(path)?;
+fn scan_pipeline(
+    path: &Path,
+    state: &state::State,
+    algo: HashType,
+    exclusions: &config::CompiledExclusions,
+    mime_filter: &mime::MimeFilter,
+) -> Result<HashMap<Hash, Vec<PathBuf>>> {
+    let size_groups = scanner::group_by_size(path, exclusions, mime_filter, state)?;
     let size_groups: Vec<Vec<PathBuf>> = size_groups
         .into_values()
         .filter(|paths| paths.len() > 1)
@@ -78,31 +191,66 @@ fn scan_pipeline(path: &Path, state: &state::State) -> Result<HashMap<Hash, Vec<
     let sparse_bar = progress("sparse hashing", size_groups.len() as u64);
     let mut sparse_groups: Vec<Vec<PathBuf>> = Vec::new();
 
+    let mut full_groups: HashMap<Hash, Vec<PathBuf>> = HashMap::new();
+
     for group in size_groups {
         sparse_bar.inc(1);
-        let sparse_hashes: Vec<(Hash, PathBuf)> = group
+        let hits: Vec<ScanHit> = group
             .par_iter()
-            .map(|path| -> Result<Option<(Hash, PathBuf)>> {
+            .map(|path| -> Result<Option<ScanHit>> {
                 let meta = std::fs::metadata(path)?;
                 let inode = meta.ino();
                 if state.is_inode_vaulted(inode)? {
                     return Ok(None);
                 }
-                let hash = hasher::sparse_hash(path, meta.len())?;
-                Ok(Some((hash, path.clone())))
+
+                // Sparse-hash every candidate, cached or not: a cached file
+                // still needs to take part in this round's bucketing, or a
+                // lone new file that duplicates it never finds its partner.
+                let sparse_hash = hasher::sparse_hash(path, meta.len(), algo)?;
+
+                let (modified, modified_nanos) = modified_from(&meta);
+                if let Some(full_hash) =
+                    state.cached_hash(path, meta.len(), modified, modified_nanos)?
+                {
+                    return Ok(Some(ScanHit::Cached {
+                        path: path.clone(),
+                        sparse_hash,
+                        full_hash,
+                    }));
+                }
+
+                Ok(Some(ScanHit::Candidate { path: path.clone(), sparse_hash }))
             })
             .collect::<Result<Vec<_>>>()?
             .into_iter()
             .flatten()
             .collect();
 
-        let mut buckets: HashMap<Hash, Vec<PathBuf>> = HashMap::new();
-        for (hash, path) in sparse_hashes {
-            buckets.entry(hash).or_default().push(path);
+        // Bucket cached and not-yet-hashed files together by sparse hash.
+        // Only candidates need full hashing; a cached file's full hash is
+        // already known, so it goes straight into `full_groups`. A bucket
+        // with just one candidate and no cached partner (or vice versa) has
+        // no duplicate this round and is dropped, same as before.
+        let mut buckets: HashMap<Hash, Vec<ScanHit>> = HashMap::new();
+        for hit in hits {
+            buckets.entry(hit.sparse_hash()).or_default().push(hit);
         }
-        for (_, paths) in buckets {
-            if paths.len() > 1 {
-                sparse_groups.push(paths);
+        for (_, bucket) in buckets {
+            if bucket.len() < 2 {
+                continue;
+            }
+            let mut candidates = Vec::new();
+            for hit in bucket {
+                match hit {
+                    ScanHit::Cached { path, full_hash, .. } => {
+                        full_groups.entry(full_hash).or_default().push(path);
+                    }
+                    ScanHit::Candidate { path, .. } => candidates.push(path),
+                }
+            }
+            if !candidates.is_empty() {
+                sparse_groups.push(candidates);
             }
         }
     }
@@ -110,24 +258,24 @@ fn scan_pipeline(path: &Path, state: &state::State) -> Result<HashMap<Hash, Vec<
 
     let total_full: usize = sparse_groups.iter().map(|g| g.len()).sum();
     let full_bar = progress("full hashing", total_full as u64);
-    let mut full_groups: HashMap<Hash, Vec<PathBuf>> = HashMap::new();
 
     for group in sparse_groups {
         let full_hashes: Vec<(Hash, PathBuf, u64)> = group
             .par_iter()
             .map(|path| -> Result<(Hash, PathBuf, u64)> {
                 let meta = std::fs::metadata(path)?;
-                let hash = hasher::full_hash(path)?;
+                let hash = hasher::full_hash(path, algo)?;
                 Ok((hash, path.clone(), meta.len()))
             })
             .collect::<Result<Vec<_>>>()?;
 
         for (hash, path, size) in full_hashes {
             full_bar.inc(1);
-            let modified = file_modified(path.as_path())?;
+            let (modified, modified_nanos) = file_modified(path.as_path())?;
             let metadata = FileMetadata {
                 size,
                 modified,
+                modified_nanos,
                 hash,
             };
             state.upsert_file(&path, &metadata)?;
@@ -156,7 +304,23 @@ fn dedupe_groups(
             continue;
         }
         let master = &paths[0];
-        
+
+        // Checked before `ensure_in_vault` runs: on a non-reflink filesystem
+        // it falls back to renaming the master straight into the vault, so
+        // checking afterwards would see the master's own expected (and
+        // harmless) disappearance and misread it as external tampering.
+        // Catching a genuine type change here, before the vault move, also
+        // stops us from reflinking/renaming a symlink/FIFO into the vault
+        // in the first place, and aborts the whole group rather than
+        // linking duplicates to a vault entry seeded from bad content.
+        if !dry_run && !is_still_regular_file(master) {
+            eprintln!(
+                "SKIPPING GROUP (type changed since scan, no longer a regular file): {}",
+                master.display()
+            );
+            continue;
+        }
+
         // Handle master file: either move to vault or calculate theoretical path
         let vault_path = if dry_run {
             let theoretical_path = vault::shard_path(hash)?;
@@ -171,7 +335,7 @@ fn dedupe_groups(
         } else {
             vault::ensure_in_vault(hash, master)?
         };
-        
+
         let mut master_verified = false;
         if paranoid && !dry_run && master.exists() {
             match dedupe::compare_files(&vault_path, master) {
@@ -247,6 +411,14 @@ fn dedupe_groups(
 
         // Handle duplicates
         for path in paths.iter().skip(1) {
+            if !dry_run && !is_still_regular_file(path) {
+                eprintln!(
+                    "SKIPPING (type changed since scan, no longer a regular file): {}",
+                    path.display()
+                );
+                continue;
+            }
+
             let mut verified = false;
             if paranoid && !dry_run {
                 match dedupe::compare_files(&vault_path, path) {
@@ -328,6 +500,71 @@ fn dedupe_groups(
     Ok(())
 }
 
+/// Takes the same `scan_pipeline` groups as `dedupe_groups`, but instead of
+/// linking every duplicate back to a vault master, picks one survivor per
+/// group via `keep` and routes the rest through `delete_method`.
+fn reclaim_groups(
+    groups: &HashMap<Hash, Vec<PathBuf>>,
+    delete_method: reclaim::DeleteMethod,
+    trash_dir: Option<&Path>,
+    keep: reclaim::KeepPolicy,
+    paranoid: bool,
+    dry_run: bool,
+) -> Result<()> {
+    for paths in groups.values() {
+        if paths.len() < 2 {
+            continue;
+        }
+
+        let keeper_index = reclaim::pick_keeper(keep, paths)?;
+        let keeper = &paths[keeper_index];
+
+        for (index, path) in paths.iter().enumerate() {
+            if index == keeper_index {
+                continue;
+            }
+
+            if !is_still_regular_file(path) {
+                eprintln!(
+                    "SKIPPING (type changed since scan, no longer a regular file): {}",
+                    path.display()
+                );
+                continue;
+            }
+
+            if paranoid && !dry_run {
+                match dedupe::compare_files(keeper, path) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        eprintln!("HASH COLLISION OR BIT ROT DETECTED: {}", path.display());
+                        continue;
+                    }
+                    Err(err) => {
+                        eprintln!("VERIFY FAILED (skipping): {}: {err}", path.display());
+                        continue;
+                    }
+                }
+            }
+
+            let name = display_name(path);
+            if dry_run {
+                println!(
+                    "{} Would {} duplicate: {} (keeping {})",
+                    "[DRY RUN]".yellow().dimmed(),
+                    delete_method.action_word(),
+                    name,
+                    display_name(keeper)
+                );
+                continue;
+            }
+
+            reclaim::remove_file(delete_method, trash_dir, path)?;
+            println!("{} {}", delete_method.tag().bold().red(), name);
+        }
+    }
+    Ok(())
+}
+
 fn display_name(path: &Path) -> String {
     path.file_name()
         .and_then(|name| name.to_str())
@@ -335,6 +572,15 @@ fn display_name(path: &Path) -> String {
         .unwrap_or_else(|| path.display().to_string())
 }
 
+/// Guards against a path changing kind between scan and dedupe (e.g. a
+/// regular file replaced by a symlink or a FIFO underneath us): we must
+/// never hardlink/reflink over anything but the regular file we scanned.
+fn is_still_regular_file(path: &Path) -> bool {
+    std::fs::symlink_metadata(path)
+        .map(|meta| meta.is_file())
+        .unwrap_or(false)
+}
+
 fn is_temp_file(path: &Path) -> bool {
     path.file_name()
         .and_then(|name| name.to_str())
@@ -342,13 +588,30 @@ fn is_temp_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-fn file_modified(path: &Path) -> Result<u64> {
+/// Outcome of checking one scan candidate against the incremental cache.
+/// Both variants carry a sparse hash so cached and not-yet-hashed files
+/// bucket together; `Cached` additionally carries the full hash already on
+/// record, skipping a redundant full-hash pass.
+enum ScanHit {
+    Cached { path: PathBuf, sparse_hash: Hash, full_hash: Hash },
+    Candidate { path: PathBuf, sparse_hash: Hash },
+}
+
+impl ScanHit {
+    fn sparse_hash(&self) -> Hash {
+        match self {
+            ScanHit::Cached { sparse_hash, .. } | ScanHit::Candidate { sparse_hash, .. } => *sparse_hash,
+        }
+    }
+}
+
+fn modified_from(meta: &std::fs::Metadata) -> (u64, u32) {
+    (meta.mtime().max(0) as u64, meta.mtime_nsec().clamp(0, 999_999_999) as u32)
+}
+
+fn file_modified(path: &Path) -> Result<(u64, u32)> {
     let metadata = std::fs::metadata(path).with_context(|| "read metadata")?;
-    let modified = metadata.modified().with_context(|| "read modified time")?;
-    let duration = modified
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default();
-    Ok(duration.as_secs())
+    Ok(modified_from(&metadata))
 }
 
 fn progress(label: &str, total: u64) -> ProgressBar {
@@ -366,3 +629,50 @@ fn print_summary(mode: &str, groups: &HashMap<Hash, Vec<PathBuf>>) {
     let duplicates = groups.values().filter(|g| g.len() > 1).count();
     println!("{mode} complete. duplicate groups: {duplicates}");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompiledExclusions;
+    use crate::mime::MimeFilter;
+    use crate::state::State;
+
+    #[test]
+    fn cached_file_still_finds_a_newly_appeared_duplicate() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = State::open(&tmp.path().join("state.db")).unwrap();
+        let exclusions = CompiledExclusions::empty();
+        let mime_filter = MimeFilter::default();
+
+        let data_dir = tmp.path().join("data");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("a"), b"duplicate content").unwrap();
+
+        // `a` has no same-size partner yet, so the first scan neither groups
+        // nor hashes it; nothing to dedupe.
+        let groups =
+            scan_pipeline(&data_dir, &state, HashType::Blake3, &exclusions, &mime_filter).unwrap();
+        assert!(groups.values().all(|g| g.len() < 2));
+
+        // Cache `a`'s full hash, as an earlier scan that hashed it would have.
+        let a = data_dir.join("a");
+        let (modified, modified_nanos) = file_modified(&a).unwrap();
+        let metadata = FileMetadata {
+            size: std::fs::metadata(&a).unwrap().len(),
+            modified,
+            modified_nanos,
+            hash: hasher::full_hash(&a, HashType::Blake3).unwrap(),
+        };
+        state.upsert_file(&a, &metadata).unwrap();
+
+        // A byte-identical copy shows up after the cache is warm.
+        std::fs::write(data_dir.join("a.copy"), b"duplicate content").unwrap();
+
+        let groups =
+            scan_pipeline(&data_dir, &state, HashType::Blake3, &exclusions, &mime_filter).unwrap();
+        assert!(
+            groups.values().any(|g| g.len() == 2),
+            "cached file and its new duplicate should end up in the same group"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+
+/// A single `type/subtype` pattern from `--only`/`--skip`, where `subtype`
+/// may be `*` to match the whole type (e.g. `image/*`).
+#[derive(Debug, Clone)]
+struct MimePattern {
+    type_: String,
+    subtype: String,
+}
+
+impl MimePattern {
+    fn parse(raw: &str) -> Result<Self> {
+        let (type_, subtype) = raw
+            .split_once('/')
+            .with_context(|| format!("invalid mime pattern {raw:?}, expected 'type/subtype'"))?;
+        Ok(Self {
+            type_: type_.to_string(),
+            subtype: subtype.to_string(),
+        })
+    }
+
+    fn matches(&self, mime: &str) -> bool {
+        let Some((type_, subtype)) = mime.split_once('/') else {
+            return false;
+        };
+        self.type_ == type_ && (self.subtype == "*" || self.subtype == subtype)
+    }
+}
+
+/// Content-type allowlist/denylist evaluated against each file's sniffed
+/// MIME type during the scan.
+#[derive(Debug, Clone, Default)]
+pub struct MimeFilter {
+    only: Vec<MimePattern>,
+    skip: Vec<MimePattern>,
+}
+
+impl MimeFilter {
+    pub fn parse(only: &str, skip: &str) -> Result<Self> {
+        Ok(Self {
+            only: split_patterns(only)?,
+            skip: split_patterns(skip)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.skip.is_empty()
+    }
+
+    /// `mime` is `None` when sniffing couldn't determine a type: such a file
+    /// passes an empty/non-matching `--skip`, but never passes `--only`,
+    /// since nothing confirmed it belongs.
+    pub fn allows(&self, mime: Option<&str>) -> bool {
+        if let Some(mime) = mime {
+            if self.skip.iter().any(|pattern| pattern.matches(mime)) {
+                return false;
+            }
+        }
+        if self.only.is_empty() {
+            return true;
+        }
+        match mime {
+            Some(mime) => self.only.iter().any(|pattern| pattern.matches(mime)),
+            None => false,
+        }
+    }
+}
+
+fn split_patterns(raw: &str) -> Result<Vec<MimePattern>> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(MimePattern::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_filter_allows_matching_subtype_and_wildcard_type() {
+        let filter = MimeFilter::parse("image/*,video/mp4", "").unwrap();
+        assert!(filter.allows(Some("image/png")));
+        assert!(filter.allows(Some("video/mp4")));
+        assert!(!filter.allows(Some("video/webm")));
+    }
+
+    #[test]
+    fn skip_filter_takes_priority_over_only() {
+        let filter = MimeFilter::parse("image/*", "image/gif").unwrap();
+        assert!(filter.allows(Some("image/png")));
+        assert!(!filter.allows(Some("image/gif")));
+    }
+
+    #[test]
+    fn undetected_mime_passes_skip_but_never_only() {
+        let empty = MimeFilter::parse("", "").unwrap();
+        assert!(empty.allows(None));
+
+        let only = MimeFilter::parse("image/*", "").unwrap();
+        assert!(!only.allows(None));
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// How a duplicate that isn't the kept copy gets removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DeleteMethod {
+    /// Unlink it outright.
+    Delete,
+    /// Move it to the XDG trash, or `--trash-dir` if one was given.
+    Trash,
+}
+
+impl DeleteMethod {
+    pub fn action_word(self) -> &'static str {
+        match self {
+            DeleteMethod::Delete => "delete",
+            DeleteMethod::Trash => "trash",
+        }
+    }
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            DeleteMethod::Delete => "[DELETED ]",
+            DeleteMethod::Trash => "[TRASHED ]",
+        }
+    }
+}
+
+/// Which path in a duplicate group survives a reclaim pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeepPolicy {
+    /// Whatever the scan happened to list first.
+    First,
+    /// The most recently modified copy.
+    Newest,
+    /// The copy with the shortest path, ties broken by scan order.
+    ShortestPath,
+}
+
+/// Picks which index in `paths` should be kept; every other path is routed
+/// through the chosen `DeleteMethod`.
+pub fn pick_keeper(policy: KeepPolicy, paths: &[PathBuf]) -> Result<usize> {
+    match policy {
+        KeepPolicy::First => Ok(0),
+        KeepPolicy::ShortestPath => Ok(paths
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, path)| path.as_os_str().len())
+            .map(|(index, _)| index)
+            .unwrap_or(0)),
+        KeepPolicy::Newest => {
+            let mut best = 0;
+            let mut best_mtime = std::fs::metadata(&paths[0])
+                .with_context(|| format!("stat {}", paths[0].display()))?
+                .modified()?;
+            for (index, path) in paths.iter().enumerate().skip(1) {
+                let mtime = std::fs::metadata(path)
+                    .with_context(|| format!("stat {}", path.display()))?
+                    .modified()?;
+                if mtime > best_mtime {
+                    best = index;
+                    best_mtime = mtime;
+                }
+            }
+            Ok(best)
+        }
+    }
+}
+
+pub fn remove_file(method: DeleteMethod, trash_dir: Option<&Path>, path: &Path) -> Result<()> {
+    match method {
+        DeleteMethod::Delete => {
+            std::fs::remove_file(path).with_context(|| format!("delete {}", path.display()))
+        }
+        DeleteMethod::Trash => trash_file(trash_dir, path),
+    }
+}
+
+fn trash_file(trash_dir: Option<&Path>, path: &Path) -> Result<()> {
+    match trash_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("create trash dir {}", dir.display()))?;
+            let dest = unique_destination(dir, path);
+            std::fs::rename(path, &dest)
+                .with_context(|| format!("move {} to {}", path.display(), dest.display()))
+        }
+        None => trash::delete(path)
+            .with_context(|| format!("move {} to XDG trash", path.display())),
+    }
+}
+
+fn unique_destination(dir: &Path, source: &Path) -> PathBuf {
+    let name = source.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let mut dest = dir.join(&name);
+    let mut suffix = 1;
+    while dest.exists() {
+        dest = dir.join(format!("{name}.{suffix}"));
+        suffix += 1;
+    }
+    dest
+}
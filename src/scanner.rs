@@ -0,0 +1,114 @@
+use crate::config::CompiledExclusions;
+use crate::mime::MimeFilter;
+use crate::state::State;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs::FileType;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// What a directory entry turned out to be. Only `Regular` is safe to hash
+/// and later hardlink/reflink over: symlinks can point outside the tree
+/// entirely, FIFOs/sockets can block forever on open, and device nodes
+/// aren't file content at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Regular,
+    Directory,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Other,
+}
+
+pub fn classify(file_type: FileType) -> FileKind {
+    if file_type.is_file() {
+        FileKind::Regular
+    } else if file_type.is_dir() {
+        FileKind::Directory
+    } else if file_type.is_symlink() {
+        FileKind::Symlink
+    } else if file_type.is_fifo() {
+        FileKind::Fifo
+    } else if file_type.is_socket() {
+        FileKind::Socket
+    } else if file_type.is_block_device() {
+        FileKind::BlockDevice
+    } else if file_type.is_char_device() {
+        FileKind::CharDevice
+    } else {
+        FileKind::Other
+    }
+}
+
+/// Walks `root` and groups regular files by size, the cheapest possible
+/// filter before any hashing happens. Every entry is classified off
+/// `symlink_metadata` (never followed) so symlinks, FIFOs, sockets, and
+/// device nodes are logged and skipped rather than handed to the hasher.
+/// `exclusions` prunes ignored directories out of the walk entirely and
+/// drops files that fail the glob or size filters before they're grouped.
+/// `mime_filter` additionally sniffs each surviving file's content type
+/// against `--only`/`--skip`; the detected type is cached in `state` keyed
+/// on size+mtime so incremental runs don't re-sniff unchanged files.
+pub fn group_by_size(
+    root: &Path,
+    exclusions: &CompiledExclusions,
+    mime_filter: &MimeFilter,
+    state: &State,
+) -> Result<HashMap<u64, Vec<PathBuf>>> {
+    let mut groups: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        if entry.depth() == 0 || !entry.file_type().is_dir() {
+            return true;
+        }
+        let name = entry.file_name().to_string_lossy();
+        !exclusions.is_dir_ignored(&name)
+    });
+
+    for entry in walker {
+        let entry = entry.with_context(|| format!("walk {}", root.display()))?;
+        let path = entry.path();
+
+        let meta = std::fs::symlink_metadata(path)
+            .with_context(|| format!("lstat {}", path.display()))?;
+        match classify(meta.file_type()) {
+            FileKind::Regular => {}
+            FileKind::Directory => continue,
+            kind => {
+                eprintln!("skipping {kind:?} (not a regular file): {}", path.display());
+                continue;
+            }
+        }
+
+        if exclusions.is_path_excluded(path) {
+            continue;
+        }
+        if exclusions.is_size_excluded(meta.len()) {
+            continue;
+        }
+
+        if !mime_filter.is_empty() {
+            let modified = meta.mtime().max(0) as u64;
+            let modified_nanos = meta.mtime_nsec().clamp(0, 999_999_999) as u32;
+            let mime = match state.cached_mime(path, meta.len(), modified, modified_nanos)? {
+                Some(cached) => cached,
+                None => {
+                    let detected = tree_magic_mini::from_filepath(path).map(str::to_string);
+                    state.cache_mime(path, meta.len(), modified, modified_nanos, detected.clone())?;
+                    detected
+                }
+            };
+            if !mime_filter.allows(mime.as_deref()) {
+                continue;
+            }
+        }
+
+        groups.entry(meta.len()).or_default().push(path.to_path_buf());
+    }
+
+    Ok(groups)
+}
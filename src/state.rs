@@ -0,0 +1,293 @@
+use crate::types::{FileMetadata, Hash, HashType};
+use anyhow::{bail, Context, Result};
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+
+const HASH_ALGO_KEY: &[u8] = b"meta:hash_algo";
+
+/// What's actually stored per path: the metadata plus the wall-clock second
+/// we wrote it, so `cached_hash` can detect the second-ambiguous case.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    metadata: FileMetadata,
+    cached_at: u64,
+}
+
+/// Detected MIME type for a path at a given size/mtime, cached independently
+/// of `CacheEntry` since type sniffing happens during the scan itself, well
+/// before a file's sparse/full hash (and therefore its `CacheEntry`) exists.
+/// Carries `cached_at` for the same second-ambiguous guard `cached_hash`
+/// uses, so a whole-second-mtime filesystem can't serve a stale MIME type
+/// for a file rewritten within the second it was sniffed.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MimeCacheEntry {
+    size: u64,
+    modified: u64,
+    modified_nanos: u32,
+    mime: Option<String>,
+    cached_at: u64,
+}
+
+/// Persistent run state: which inodes are already vault members, the
+/// size/mtime/hash cache used for incremental scans, and CAS refcounts.
+/// Backed by sled so a single `State` handle is safe to share across the
+/// rayon worker pool.
+pub struct State {
+    db: sled::Db,
+}
+
+impl State {
+    pub fn open_default() -> Result<Self> {
+        Self::open(&default_state_path()?)
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let db = sled::open(path).with_context(|| format!("open state db at {}", path.display()))?;
+        Ok(Self { db })
+    }
+
+    /// Records the hash algorithm a fresh db was built with, and refuses to
+    /// continue if a later run picks a different one: mixed digests in the
+    /// same db would make shard paths and refcount keys ambiguous.
+    pub fn check_or_set_hash_algo(&self, algo: HashType) -> Result<()> {
+        match self.db.get(HASH_ALGO_KEY)? {
+            Some(stored) => {
+                let stored = std::str::from_utf8(&stored).unwrap_or("?");
+                if stored != algo.as_str() {
+                    bail!(
+                        "state db was built with --hash {stored}, refusing to mix in {algo}; \
+                         rerun with --hash {stored} or delete the state db to start over"
+                    );
+                }
+            }
+            None => {
+                self.db.insert(HASH_ALGO_KEY, algo.as_str().as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_inode_vaulted(&self, inode: u64) -> Result<bool> {
+        Ok(self.db.contains_key(inode_key(inode))?)
+    }
+
+    pub fn mark_inode_vaulted(&self, inode: u64) -> Result<()> {
+        self.db.insert(inode_key(inode), &[1u8])?;
+        Ok(())
+    }
+
+    pub fn upsert_file(&self, path: &Path, metadata: &FileMetadata) -> Result<()> {
+        let entry = CacheEntry {
+            metadata: metadata.clone(),
+            cached_at: now_secs(),
+        };
+        let value = bincode::serialize(&entry).context("encode file metadata")?;
+        self.db.insert(file_key(path), value)?;
+        Ok(())
+    }
+
+    pub fn get_file(&self, path: &Path) -> Result<Option<FileMetadata>> {
+        match self.db.get(file_key(path))? {
+            Some(bytes) => {
+                let entry: CacheEntry =
+                    bincode::deserialize(&bytes).context("decode file metadata")?;
+                Ok(Some(entry.metadata))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Incremental-scan fast path: if `path` still has the size and mtime we
+    /// last recorded, return the cached hash instead of making the caller
+    /// re-read the file.
+    ///
+    /// `file_modified` truncates to whole seconds on filesystems without
+    /// sub-second mtimes, so a file touched twice within the same second as
+    /// the scan that cached it would otherwise look unchanged forever. We
+    /// guard against that (dirstate-v2's "second-ambiguous" trick): if the
+    /// cached mtime falls in the same wall-clock second the entry was
+    /// written, treat it as ambiguous and force a re-hash. Sub-second mtimes
+    /// sidestep the whole problem, so we trust those outright.
+    pub fn cached_hash(
+        &self,
+        path: &Path,
+        size: u64,
+        modified: u64,
+        modified_nanos: u32,
+    ) -> Result<Option<Hash>> {
+        let Some(bytes) = self.db.get(file_key(path))? else {
+            return Ok(None);
+        };
+        let entry: CacheEntry = bincode::deserialize(&bytes).context("decode file metadata")?;
+
+        if entry.metadata.size != size || entry.metadata.modified != modified {
+            return Ok(None);
+        }
+
+        if entry.metadata.modified_nanos != 0 || modified_nanos != 0 {
+            return Ok((entry.metadata.modified_nanos == modified_nanos).then_some(entry.metadata.hash));
+        }
+
+        if entry.metadata.modified >= entry.cached_at {
+            return Ok(None);
+        }
+        Ok(Some(entry.metadata.hash))
+    }
+
+    pub fn set_cas_refcount(&self, hash: &Hash, count: u64) -> Result<()> {
+        self.db.insert(refcount_key(hash), &count.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// `Some(mime)` (possibly `None` inside, meaning "sniffed, but
+    /// undetected") if `path` still has the size/mtime we sniffed it at;
+    /// `None` if it's unseen or stale and needs re-sniffing. Subject to the
+    /// same second-ambiguous guard as `cached_hash`: on a whole-second-mtime
+    /// filesystem, a file rewritten within the second it was sniffed must
+    /// not be trusted as unchanged.
+    pub fn cached_mime(
+        &self,
+        path: &Path,
+        size: u64,
+        modified: u64,
+        modified_nanos: u32,
+    ) -> Result<Option<Option<String>>> {
+        let Some(bytes) = self.db.get(mime_key(path))? else {
+            return Ok(None);
+        };
+        let entry: MimeCacheEntry = bincode::deserialize(&bytes).context("decode mime cache entry")?;
+
+        if entry.size != size || entry.modified != modified {
+            return Ok(None);
+        }
+
+        if entry.modified_nanos != 0 || modified_nanos != 0 {
+            return Ok((entry.modified_nanos == modified_nanos).then_some(entry.mime));
+        }
+
+        if entry.modified >= entry.cached_at {
+            return Ok(None);
+        }
+        Ok(Some(entry.mime))
+    }
+
+    pub fn cache_mime(
+        &self,
+        path: &Path,
+        size: u64,
+        modified: u64,
+        modified_nanos: u32,
+        mime: Option<String>,
+    ) -> Result<()> {
+        let entry = MimeCacheEntry { size, modified, modified_nanos, mime, cached_at: now_secs() };
+        let value = bincode::serialize(&entry).context("encode mime cache entry")?;
+        self.db.insert(mime_key(path), value)?;
+        Ok(())
+    }
+}
+
+fn default_state_path() -> Result<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "imprint").context("resolve state directory")?;
+    Ok(dirs.data_dir().join("state.db"))
+}
+
+fn inode_key(inode: u64) -> Vec<u8> {
+    let mut key = b"inode:".to_vec();
+    key.extend_from_slice(&inode.to_le_bytes());
+    key
+}
+
+fn file_key(path: &Path) -> Vec<u8> {
+    let mut key = b"file:".to_vec();
+    key.extend_from_slice(path.to_string_lossy().as_bytes());
+    key
+}
+
+fn refcount_key(hash: &Hash) -> Vec<u8> {
+    let mut key = b"refcount:".to_vec();
+    key.push(hash.algo as u8);
+    key.extend_from_slice(hash.as_bytes());
+    key
+}
+
+fn mime_key(path: &Path) -> Vec<u8> {
+    let mut key = b"mime:".to_vec();
+    key.extend_from_slice(path.to_string_lossy().as_bytes());
+    key
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::HashType;
+
+    fn open_temp() -> (State, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let state = State::open(&dir.path().join("state.db")).unwrap();
+        (state, dir)
+    }
+
+    #[test]
+    fn cached_hash_trusts_sub_second_mtime_even_within_the_same_second() {
+        let (state, _dir) = open_temp();
+        let path = Path::new("/example/sub-second");
+        let hash = Hash::new(HashType::Blake3, &[1u8; 32]);
+        let metadata = FileMetadata { size: 10, modified: now_secs(), modified_nanos: 123, hash };
+        state.upsert_file(path, &metadata).unwrap();
+
+        let same = state.cached_hash(path, 10, metadata.modified, 123).unwrap();
+        assert_eq!(same, Some(hash));
+
+        // Different sub-second mtime at the same whole second: a real
+        // rewrite, not the cached one, so the cache must miss.
+        let rewritten = state.cached_hash(path, 10, metadata.modified, 456).unwrap();
+        assert_eq!(rewritten, None);
+    }
+
+    #[test]
+    fn cached_hash_is_ambiguous_within_the_same_whole_second_without_nanos() {
+        let (state, _dir) = open_temp();
+        let path = Path::new("/example/whole-second");
+        let hash = Hash::new(HashType::Blake3, &[2u8; 32]);
+        let metadata = FileMetadata { size: 10, modified: now_secs(), modified_nanos: 0, hash };
+        state.upsert_file(path, &metadata).unwrap();
+
+        // No sub-second resolution, and the entry was cached in the same
+        // wall-clock second as `modified`: can't rule out a same-second
+        // rewrite, so the cache must force a re-hash rather than trust it.
+        let ambiguous = state.cached_hash(path, 10, metadata.modified, 0).unwrap();
+        assert_eq!(ambiguous, None);
+    }
+
+    #[test]
+    fn cached_mime_is_ambiguous_within_the_same_whole_second_without_nanos() {
+        let (state, _dir) = open_temp();
+        let path = Path::new("/example/mime-whole-second");
+        let modified = now_secs();
+        state.cache_mime(path, 10, modified, 0, Some("text/plain".to_string())).unwrap();
+
+        let ambiguous = state.cached_mime(path, 10, modified, 0).unwrap();
+        assert_eq!(ambiguous, None);
+    }
+
+    #[test]
+    fn cached_mime_trusts_sub_second_mtime_even_within_the_same_second() {
+        let (state, _dir) = open_temp();
+        let path = Path::new("/example/mime-sub-second");
+        let modified = now_secs();
+        state.cache_mime(path, 10, modified, 123, Some("text/plain".to_string())).unwrap();
+
+        let same = state.cached_mime(path, 10, modified, 123).unwrap();
+        assert_eq!(same, Some(Some("text/plain".to_string())));
+
+        let rewritten = state.cached_mime(path, 10, modified, 456).unwrap();
+        assert_eq!(rewritten, None);
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest digest we ever store; Blake3 (32 bytes) is currently the widest.
+pub const MAX_HASH_BYTES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HashType {
+    Blake3,
+    Xxh3,
+    Crc32,
+}
+
+impl HashType {
+    pub const ALL: [HashType; 3] = [HashType::Blake3, HashType::Xxh3, HashType::Crc32];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashType::Blake3 => "blake3",
+            HashType::Xxh3 => "xxh3",
+            HashType::Crc32 => "crc32",
+        }
+    }
+
+    pub fn digest_len(self) -> usize {
+        match self {
+            HashType::Blake3 => 32,
+            HashType::Xxh3 => 8,
+            HashType::Crc32 => 4,
+        }
+    }
+}
+
+impl fmt::Display for HashType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HashType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blake3" => Ok(HashType::Blake3),
+            "xxh3" => Ok(HashType::Xxh3),
+            "crc32" => Ok(HashType::Crc32),
+            other => Err(format!(
+                "unknown hash algorithm '{other}' (expected blake3, xxh3, or crc32)"
+            )),
+        }
+    }
+}
+
+/// A digest tagged with the algorithm that produced it. `bytes` is always
+/// zero-padded past `algo.digest_len()` so derived equality/hashing stays
+/// correct without a custom impl.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Hash {
+    pub algo: HashType,
+    bytes: [u8; MAX_HASH_BYTES],
+}
+
+impl Hash {
+    pub fn new(algo: HashType, digest: &[u8]) -> Self {
+        debug_assert_eq!(digest.len(), algo.digest_len());
+        let mut bytes = [0u8; MAX_HASH_BYTES];
+        bytes[..digest.len()].copy_from_slice(digest);
+        Self { algo, bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.algo.digest_len()]
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hash({}:{})", self.algo, hash_to_hex(self))
+    }
+}
+
+pub fn hash_to_hex(hash: &Hash) -> String {
+    hash.as_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub size: u64,
+    pub modified: u64,
+    /// Sub-second component of `modified`, 0 when the filesystem doesn't
+    /// expose one. Lets the incremental scan cache skip the second-ambiguous
+    /// guard entirely when real sub-second resolution is available.
+    pub modified_nanos: u32,
+    pub hash: Hash,
+}
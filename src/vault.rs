@@ -0,0 +1,41 @@
+use crate::types::{hash_to_hex, Hash};
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+
+/// Vault master files are sharded two hex chars deep, git-object style, so a
+/// single directory never has to hold every digest in the store.
+pub fn shard_path(hash: &Hash) -> Result<PathBuf> {
+    let hex = hash_to_hex(hash);
+    let (prefix, rest) = hex.split_at(2);
+    Ok(vault_root()?
+        .join(hash.algo.as_str())
+        .join(prefix)
+        .join(rest))
+}
+
+/// Ensures the vault holds a copy of `source` under `hash`'s shard path,
+/// moving it in on first sight, and returns that path.
+pub fn ensure_in_vault(hash: &Hash, source: &Path) -> Result<PathBuf> {
+    let dest = shard_path(hash)?;
+    if dest.exists() {
+        return Ok(dest);
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("create vault shard dir {}", parent.display()))?;
+    }
+    match reflink::reflink(source, &dest) {
+        Ok(_) => {}
+        Err(_) => {
+            std::fs::rename(source, &dest)
+                .with_context(|| format!("move {} into vault", source.display()))?;
+        }
+    }
+    Ok(dest)
+}
+
+fn vault_root() -> Result<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "imprint").context("resolve vault directory")?;
+    Ok(dirs.data_dir().join("vault"))
+}